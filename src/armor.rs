@@ -0,0 +1,150 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! ASCII-armored text encoding, wrapping arbitrary binary output (ciphertext, signatures,
+//! serialised `PublicId`s, ...) in a Base64 block bounded by typed delimiters and checksummed
+//! with a trailing CRC-24, so it can be safely pasted into JSON, emails, or config files.
+
+use std::fmt;
+
+const LINE_LEN: usize = 64;
+const CRC24_INIT: u32 = 0x00b7_04ce;
+const CRC24_POLY: u32 = 0x0186_4cfb;
+
+/// The kind of data wrapped in an armored block, recorded in the `BEGIN`/`END` delimiters so
+/// that `dearmor` can reject a block of the wrong kind rather than silently misinterpreting it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    PublicId,
+    Ciphertext,
+    Signature,
+}
+
+impl Kind {
+    fn label(self) -> &'static str {
+        match self {
+            Kind::PublicId => "PUBLIC ID",
+            Kind::Ciphertext => "CIPHERTEXT",
+            Kind::Signature => "SIGNATURE",
+        }
+    }
+
+    fn from_label(label: &str) -> Option<Kind> {
+        match label {
+            "PUBLIC ID" => Some(Kind::PublicId),
+            "CIPHERTEXT" => Some(Kind::Ciphertext),
+            "SIGNATURE" => Some(Kind::Signature),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.label())
+    }
+}
+
+/// Wraps `data` in a `-----BEGIN SAFE_CRYPTO <KIND>-----` / `-----END SAFE_CRYPTO <KIND>-----`
+/// Base64 block with a trailing CRC-24 checksum line.
+pub fn armor(kind: Kind, data: &[u8]) -> String {
+    let encoded = base64::encode(data);
+    let crc = crc24(data);
+    let checksum = base64::encode(&[(crc >> 16) as u8, (crc >> 8) as u8, crc as u8]);
+
+    let mut out = format!("-----BEGIN SAFE_CRYPTO {}-----\n", kind.label());
+    for line in encoded.as_bytes().chunks(LINE_LEN) {
+        out.push_str(unwrap!(::std::str::from_utf8(line)));
+        out.push('\n');
+    }
+    out.push('=');
+    out.push_str(&checksum);
+    out.push('\n');
+    out.push_str(&format!("-----END SAFE_CRYPTO {}-----\n", kind.label()));
+    out
+}
+
+/// Parses and validates an armored block produced by `armor`, returning its kind and the
+/// original bytes. Fails if the delimiters are malformed, the kind is unrecognised, the Base64
+/// is invalid, or the trailing checksum does not match the decoded data.
+pub fn dearmor(text: &str) -> Result<(Kind, Vec<u8>), ArmorError> {
+    let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+
+    let begin = lines.next().ok_or(ArmorError::MissingHeader)?;
+    let label = begin
+        .trim_start_matches("-----BEGIN SAFE_CRYPTO ")
+        .trim_end_matches("-----");
+    if label.len() + "-----BEGIN SAFE_CRYPTO -----".len() != begin.len() {
+        return Err(ArmorError::MissingHeader);
+    }
+    let kind = Kind::from_label(label).ok_or(ArmorError::UnknownKind)?;
+
+    let rest: Vec<&str> = lines.collect();
+    let (end, body) = rest.split_last().ok_or(ArmorError::MissingFooter)?;
+    if *end != format!("-----END SAFE_CRYPTO {}-----", kind.label()) {
+        return Err(ArmorError::MissingFooter);
+    }
+
+    let (checksum_line, body) = body.split_last().ok_or(ArmorError::MissingChecksum)?;
+    if !checksum_line.starts_with('=') {
+        return Err(ArmorError::MissingChecksum);
+    }
+    let checksum_line = &checksum_line[1..];
+    let checksum_bytes = base64::decode(checksum_line).map_err(|_| ArmorError::InvalidBase64)?;
+    if checksum_bytes.len() != 3 {
+        return Err(ArmorError::MissingChecksum);
+    }
+    let checksum = (u32::from(checksum_bytes[0]) << 16)
+        | (u32::from(checksum_bytes[1]) << 8)
+        | u32::from(checksum_bytes[2]);
+
+    let data = base64::decode(&body.concat()).map_err(|_| ArmorError::InvalidBase64)?;
+    if crc24(&data) != checksum {
+        return Err(ArmorError::ChecksumMismatch);
+    }
+
+    Ok((kind, data))
+}
+
+fn crc24(data: &[u8]) -> u32 {
+    let mut crc = CRC24_INIT;
+    for &byte in data {
+        crc ^= u32::from(byte) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x0100_0000 != 0 {
+                crc ^= CRC24_POLY;
+            }
+        }
+    }
+    crc & 0x00ff_ffff
+}
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum ArmorError {
+        MissingHeader {
+            description("armored text is missing a valid BEGIN header")
+        }
+        MissingFooter {
+            description("armored text is missing a matching END footer")
+        }
+        MissingChecksum {
+            description("armored text is missing its CRC-24 checksum line")
+        }
+        UnknownKind {
+            description("armored text names an unrecognised kind")
+        }
+        InvalidBase64 {
+            description("armored text contains invalid base64")
+        }
+        ChecksumMismatch {
+            description("armored text's checksum does not match its decoded data")
+        }
+    }
+}
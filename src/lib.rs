@@ -16,33 +16,63 @@ extern crate quick_error;
 #[macro_use]
 extern crate unwrap;
 
+extern crate base64;
+#[cfg(feature = "tokio")]
+extern crate tokio;
+
+pub mod armor;
+pub mod pow;
+mod shamir;
+#[cfg(feature = "serialize-secret-keys")]
+mod serde_secret;
+mod stream;
+mod suite;
+
 use maidsafe_utilities::serialisation::{deserialise, serialise, SerialisationError};
-use rust_sodium::crypto::{box_, sealedbox, sign};
+use rust_sodium::crypto::{box_, sign};
 use serde::{de::DeserializeOwned, Serialize};
 use std::sync::Arc;
 
+pub use armor::ArmorError;
+pub use shamir::{CombineError, Shard, SplitError};
+pub use stream::{DecryptStreamError, EncryptStreamError};
+pub use suite::{CipherSuite, SodiumSuite};
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct PackedNonce {
     nonce: [u8; box_::NONCEBYTES],
     ciphertext: Vec<u8>,
+    /// Proof-of-work nonce from `SharedSecretKey::encrypt_with_pow`; `None` for messages sent via
+    /// the plain `encrypt`/`encrypt_bytes` path. Carrying this as an optional field of the normal
+    /// envelope (rather than a separate wire format) keeps stamped and unstamped messages
+    /// interchangeable: both decrypt via `decrypt`/`decrypt_bytes`, and both can be inspected by
+    /// `pow::verify_pow`/`pow::difficulty`.
+    pow_nonce: Option<u64>,
 }
 
+/// An identity's public key material, generic over the `CipherSuite` it was generated under
+/// (defaulting to `SodiumSuite`, the only suite this crate ships).
 #[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Clone)]
-pub struct PublicId {
-    sign: sign::PublicKey,
-    encrypt: box_::PublicKey,
+pub struct PublicId<S: CipherSuite = SodiumSuite> {
+    /// `S::ID` of the suite this `PublicId`'s keys were generated under, so that a peer
+    /// deserialising it can detect a suite mismatch instead of misinterpreting the key bytes.
+    suite: u8,
+    sign: S::SignPublicKey,
+    encrypt: S::EncryptPublicKey,
 }
 
+/// An identity's secret key material, generic over the `CipherSuite` it was generated under
+/// (defaulting to `SodiumSuite`, the only suite this crate ships).
 #[derive(Debug, PartialEq, Eq, Clone)]
-pub struct SecretId {
-    inner: Arc<SecretIdInner>,
-    public: PublicId,
+pub struct SecretId<S: CipherSuite = SodiumSuite> {
+    inner: Arc<SecretIdInner<S>>,
+    public: PublicId<S>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
-struct SecretIdInner {
-    sign: sign::SecretKey,
-    encrypt: box_::SecretKey,
+struct SecretIdInner<S: CipherSuite = SodiumSuite> {
+    sign: S::SignSecretKey,
+    encrypt: S::EncryptSecretKey,
 }
 
 #[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Clone)]
@@ -50,35 +80,76 @@ pub struct Signature {
     signature: sign::Signature,
 }
 
+/// A symmetric key precomputed from a `SecretId` and a peer's `PublicId`, generic over the
+/// `CipherSuite` it was derived under (defaulting to `SodiumSuite`, the only suite this crate
+/// ships).
 #[derive(Debug, PartialEq, Eq, Clone)]
-pub struct SharedSecretKey {
-    precomputed: Arc<box_::PrecomputedKey>,
+pub struct SharedSecretKey<S: CipherSuite = SodiumSuite> {
+    precomputed: Arc<S::PrecomputedKey>,
 }
 
-impl PublicId {
+impl Signature {
+    /// Serialises this `Signature` and wraps it in an ASCII-armored block.
+    pub fn to_armored(&self) -> Result<String, EncryptError> {
+        let bytes = serialise(self).map_err(EncryptError::Serialisation)?;
+        Ok(armor::armor(armor::Kind::Signature, &bytes))
+    }
+
+    /// Parses a `Signature` out of an ASCII-armored block produced by `to_armored`.
+    pub fn from_armored(text: &str) -> Result<Signature, FromArmoredError> {
+        let (kind, bytes) = armor::dearmor(text).map_err(FromArmoredError::Armor)?;
+        if kind != armor::Kind::Signature {
+            return Err(FromArmoredError::WrongKind);
+        }
+        deserialise(&bytes).map_err(FromArmoredError::Deserialisation)
+    }
+}
+
+impl<S: CipherSuite> PublicId<S> {
     pub fn encrypt_anonymous<T>(&self, plaintext: &T) -> Result<Vec<u8>, EncryptError>
     where
         T: Serialize,
     {
         let bytes = serialise(plaintext).map_err(EncryptError::Serialisation)?;
-        Ok(self.encrypt_anonymous_bytes(&bytes))
+        self.encrypt_anonymous_bytes(&bytes).map_err(EncryptError::Suite)
+    }
+
+    pub fn encrypt_anonymous_bytes(&self, plaintext: &[u8]) -> Result<Vec<u8>, SuiteError> {
+        if self.suite != S::ID {
+            return Err(SuiteError::Unsupported);
+        }
+        Ok(S::seal_anonymous(plaintext, &self.encrypt))
     }
 
-    pub fn encrypt_anonymous_bytes(&self, plaintext: &[u8]) -> Vec<u8> {
-        sealedbox::seal(plaintext, &self.encrypt)
+    /// Verifies `signature` against `data`. Returns `false` (as for any other failed
+    /// verification) if `self` was generated under a cipher suite this build doesn't support.
+    pub fn verify_detached(&self, signature: &S::Signature, data: &[u8]) -> bool {
+        self.suite == S::ID && S::verify_detached(signature, data, &self.sign)
     }
 
-    pub fn verify_detached(&self, signature: &sign::Signature, data: &[u8]) -> bool {
-        sign::verify_detached(signature, data, &self.sign)
+    /// Serialises this `PublicId` and wraps it in an ASCII-armored block.
+    pub fn to_armored(&self) -> Result<String, EncryptError> {
+        let bytes = serialise(self).map_err(EncryptError::Serialisation)?;
+        Ok(armor::armor(armor::Kind::PublicId, &bytes))
+    }
+
+    /// Parses a `PublicId` out of an ASCII-armored block produced by `to_armored`.
+    pub fn from_armored(text: &str) -> Result<PublicId<S>, FromArmoredError> {
+        let (kind, bytes) = armor::dearmor(text).map_err(FromArmoredError::Armor)?;
+        if kind != armor::Kind::PublicId {
+            return Err(FromArmoredError::WrongKind);
+        }
+        deserialise(&bytes).map_err(FromArmoredError::Deserialisation)
     }
 }
 
 #[cfg_attr(feature = "cargo-clippy", allow(new_without_default))]
-impl SecretId {
-    pub fn new() -> SecretId {
-        let (sign_pk, sign_sk) = sign::gen_keypair();
-        let (encrypt_pk, encrypt_sk) = box_::gen_keypair();
+impl<S: CipherSuite> SecretId<S> {
+    pub fn new() -> SecretId<S> {
+        let (sign_pk, sign_sk) = S::gen_sign_keypair();
+        let (encrypt_pk, encrypt_sk) = S::gen_encrypt_keypair();
         let public = PublicId {
+            suite: S::ID,
             sign: sign_pk,
             encrypt: encrypt_pk,
         };
@@ -91,7 +162,7 @@ impl SecretId {
         }
     }
 
-    pub fn public_id(&self) -> &PublicId {
+    pub fn public_id(&self) -> &PublicId<S> {
         &self.public
     }
 
@@ -109,30 +180,73 @@ impl SecretId {
     }
 
     pub fn decrypt_anonymous_bytes(&self, cyphertext: &[u8]) -> Result<Vec<u8>, DecryptBytesError> {
-        sealedbox::open(cyphertext, &self.public.encrypt, &self.inner.encrypt)
+        S::open_anonymous(cyphertext, &self.public.encrypt, &self.inner.encrypt)
             .map_err(|()| DecryptBytesError::DecryptVerify)
     }
 
-    pub fn sign_detached(&self, data: &[u8]) -> sign::Signature {
-        sign::sign_detached(data, &self.inner.sign)
+    pub fn sign_detached(&self, data: &[u8]) -> S::Signature {
+        S::sign_detached(data, &self.inner.sign)
     }
 
-    pub fn shared_key(&self, their_pk: &PublicId) -> SharedSecretKey {
-        let precomputed = box_::precompute(&their_pk.encrypt, &self.inner.encrypt);
-        SharedSecretKey {
+    /// Derives a `SharedSecretKey` for communicating with `their_pk`. Fails if `their_pk` was
+    /// generated under a cipher suite this build doesn't support.
+    pub fn shared_key(&self, their_pk: &PublicId<S>) -> Result<SharedSecretKey<S>, SuiteError> {
+        if their_pk.suite != S::ID {
+            return Err(SuiteError::Unsupported);
+        }
+        let precomputed = S::precompute(&their_pk.encrypt, &self.inner.encrypt);
+        Ok(SharedSecretKey {
             precomputed: Arc::new(precomputed),
+        })
+    }
+
+    /// Splits this identity's secret key material into `n` shards, any `k` of which are
+    /// sufficient to reconstruct it via `SecretId::from_shares`.
+    pub fn shares(&self, k: u8, n: u8) -> Result<Vec<Shard>, SplitError> {
+        let mut bytes =
+            Vec::with_capacity(S::SIGN_SECRET_KEY_BYTES + S::ENCRYPT_SECRET_KEY_BYTES);
+        bytes.extend_from_slice(S::sign_secret_key_bytes(&self.inner.sign));
+        bytes.extend_from_slice(S::encrypt_secret_key_bytes(&self.inner.encrypt));
+        shamir::split(&bytes, k, n)
+    }
+
+    /// Reconstructs a `SecretId` from `k` or more shards produced by `SecretId::shares`.
+    pub fn from_shares(shards: &[Shard]) -> Result<SecretId<S>, CombineError> {
+        let bytes = shamir::combine(shards)?;
+        if bytes.len() != S::SIGN_SECRET_KEY_BYTES + S::ENCRYPT_SECRET_KEY_BYTES {
+            return Err(CombineError::InvalidLength);
         }
+
+        let sign_sk = S::sign_secret_key_from_bytes(&bytes[..S::SIGN_SECRET_KEY_BYTES]);
+        let encrypt_sk = S::encrypt_secret_key_from_bytes(&bytes[S::SIGN_SECRET_KEY_BYTES..]);
+
+        let public = derive_public_id::<S>(&sign_sk, &encrypt_sk);
+        Ok(SecretId {
+            public,
+            inner: Arc::new(SecretIdInner {
+                sign: sign_sk,
+                encrypt: encrypt_sk,
+            }),
+        })
     }
 }
 
-impl SharedSecretKey {
+/// Derives the `PublicId` matching a pair of secret keys, via `CipherSuite::derive_public_keys`.
+fn derive_public_id<S: CipherSuite>(
+    sign_sk: &S::SignSecretKey,
+    encrypt_sk: &S::EncryptSecretKey,
+) -> PublicId<S> {
+    let (sign, encrypt) = S::derive_public_keys(sign_sk, encrypt_sk);
+    PublicId {
+        suite: S::ID,
+        sign,
+        encrypt,
+    }
+}
+
+impl<S: CipherSuite> SharedSecretKey<S> {
     pub fn encrypt_bytes(&self, plaintext: &[u8]) -> Result<Vec<u8>, EncryptError> {
-        let nonce = box_::gen_nonce();
-        let ciphertext = box_::seal_precomputed(plaintext, &nonce, &self.precomputed);
-        Ok(serialise(&PackedNonce {
-            nonce: nonce.0,
-            ciphertext,
-        }).map_err(EncryptError::Serialisation)?)
+        Ok(S::seal_precomputed(plaintext, &self.precomputed))
     }
 
     pub fn encrypt<T>(&self, plaintext: &T) -> Result<Vec<u8>, EncryptError>
@@ -144,10 +258,7 @@ impl SharedSecretKey {
     }
 
     pub fn decrypt_bytes(&self, encoded: &[u8]) -> Result<Vec<u8>, DecryptBytesError> {
-        let PackedNonce { nonce, ciphertext } =
-            deserialise(encoded).map_err(DecryptBytesError::Deserialisation)?;
-        box_::open_precomputed(&ciphertext, &box_::Nonce(nonce), &self.precomputed)
-            .map_err(|()| DecryptBytesError::DecryptVerify)
+        S::open_precomputed(encoded, &self.precomputed).map_err(|()| DecryptBytesError::DecryptVerify)
     }
 
     pub fn decrypt<T>(&self, cyphertext: &[u8]) -> Result<T, DecryptError>
@@ -170,6 +281,20 @@ quick_error! {
             display("error serializing message: {}", e)
             cause(e)
         }
+        Suite(e: SuiteError) {
+            description("error encrypting for recipient's cipher suite")
+            display("error encrypting for recipient's cipher suite: {}", e)
+            cause(e)
+        }
+    }
+}
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum SuiteError {
+        Unsupported {
+            description("public key uses a cipher suite this build does not support")
+        }
     }
 }
 
@@ -200,3 +325,35 @@ quick_error! {
         }
     }
 }
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum FromArmoredError {
+        Armor(e: ArmorError) {
+            description("error parsing armored text")
+            display("error parsing armored text: {}", e)
+            cause(e)
+        }
+        WrongKind {
+            description("armored text is of the wrong kind")
+        }
+        Deserialisation(e: SerialisationError) {
+            description("error deserializing armored data")
+            display("error deserializing armored data: {}", e)
+            cause(e)
+        }
+    }
+}
+
+#[cfg(feature = "serialize-secret-keys")]
+quick_error! {
+    #[derive(Debug)]
+    pub enum DeserialiseSecretError {
+        InvalidLength {
+            description("deserialised key material has the wrong length")
+        }
+        PublicKeyMismatch {
+            description("deserialised public key does not match the deserialised secret key")
+        }
+    }
+}
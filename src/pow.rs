@@ -0,0 +1,117 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Optional proof-of-work stamping for `SharedSecretKey` messages, so that a recipient in an
+//! open peer-to-peer setting can cheaply reject under-powered messages before spending CPU on an
+//! attempt to decrypt them. A stamped message embeds a 64-bit nonce in its `PackedNonce` envelope
+//! such that `sha256(nonce || ciphertext)` has at least the requested number of leading zero
+//! bits. The nonce is just the envelope's optional `pow_nonce` field, so a stamped message is a
+//! perfectly ordinary `PackedNonce` to `SharedSecretKey::decrypt`/`decrypt_bytes` — there is no
+//! separate wire format or decrypt path to maintain.
+
+use maidsafe_utilities::serialisation::{deserialise, serialise};
+use rust_sodium::crypto::box_;
+use rust_sodium::crypto::hash::sha256;
+
+use {PackedNonce, SharedSecretKey};
+
+/// `sha256` produces a 256-bit digest, so no nonce can ever satisfy a higher target.
+const MAX_TARGET_BITS: u32 = 256;
+
+impl SharedSecretKey {
+    /// Encrypts `plaintext` as with `encrypt_bytes`, then searches for a nonce such that
+    /// `sha256(nonce || ciphertext)` has at least `target_bits` leading zero bits, embedding the
+    /// winning nonce in the returned message's envelope.
+    pub fn encrypt_with_pow(&self, plaintext: &[u8], target_bits: u32) -> Result<Vec<u8>, PowError> {
+        if target_bits > MAX_TARGET_BITS {
+            return Err(PowError::TargetTooHigh);
+        }
+
+        let nonce = box_::gen_nonce();
+        let ciphertext = box_::seal_precomputed(plaintext, &nonce, &self.precomputed);
+        let pow_nonce = find_pow_nonce(&ciphertext, target_bits).ok_or(PowError::Exhausted)?;
+
+        Ok(unwrap!(serialise(&PackedNonce {
+            nonce: nonce.0,
+            ciphertext,
+            pow_nonce: Some(pow_nonce),
+        })))
+    }
+}
+
+/// Cheaply checks, without attempting to decrypt, whether `message` carries a proof-of-work
+/// stamp of at least `min_bits` leading zero bits. An unstamped message only passes when
+/// `min_bits` is zero; a message that fails to parse never passes.
+pub fn verify_pow(message: &[u8], min_bits: u32) -> bool {
+    let packed: PackedNonce = match deserialise(message) {
+        Ok(packed) => packed,
+        Err(_) => return false,
+    };
+    match packed.pow_nonce {
+        Some(nonce) => leading_zero_bits(&pow_hash(nonce, &packed.ciphertext).0) >= min_bits,
+        None => min_bits == 0,
+    }
+}
+
+/// Measures the number of leading zero bits of `message`'s proof-of-work stamp, or `None` if the
+/// message is unstamped or fails to parse.
+pub fn difficulty(message: &[u8]) -> Option<u32> {
+    let packed: PackedNonce = deserialise(message).ok()?;
+    let nonce = packed.pow_nonce?;
+    Some(leading_zero_bits(&pow_hash(nonce, &packed.ciphertext).0))
+}
+
+/// Searches the full 64-bit nonce space for one satisfying `target_bits`, returning `None` if
+/// none does (only possible for a `target_bits` close to `MAX_TARGET_BITS`).
+fn find_pow_nonce(ciphertext: &[u8], target_bits: u32) -> Option<u64> {
+    let mut nonce = 0u64;
+    loop {
+        if leading_zero_bits(&pow_hash(nonce, ciphertext).0) >= target_bits {
+            return Some(nonce);
+        }
+        let (next, exhausted) = nonce.overflowing_add(1);
+        if exhausted {
+            return None;
+        }
+        nonce = next;
+    }
+}
+
+fn pow_hash(nonce: u64, ciphertext: &[u8]) -> sha256::Digest {
+    let mut input = Vec::with_capacity(8 + ciphertext.len());
+    for i in 0..8 {
+        input.push((nonce >> (8 * i)) as u8);
+    }
+    input.extend_from_slice(ciphertext);
+    sha256::hash(&input)
+}
+
+fn leading_zero_bits(bytes: &[u8]) -> u32 {
+    let mut count = 0;
+    for &byte in bytes {
+        if byte == 0 {
+            count += 8;
+        } else {
+            count += byte.leading_zeros();
+            break;
+        }
+    }
+    count
+}
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum PowError {
+        TargetTooHigh {
+            description("target_bits exceeds the maximum a sha256 digest can ever satisfy")
+        }
+        Exhausted {
+            description("no nonce in the 64-bit search space satisfies target_bits")
+        }
+    }
+}
@@ -0,0 +1,88 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! `Serialize`/`Deserialize` impls for `SecretId` and `SharedSecretKey`, gated behind the
+//! `serialize-secret-keys` feature so that secret key material can't be serialised by accident.
+
+use rust_sodium::crypto::{box_, sign};
+use serde::de::Error as DeError;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::sync::Arc;
+
+use derive_public_id;
+use {DeserialiseSecretError, PublicId, SecretId, SecretIdInner, SharedSecretKey, SodiumSuite};
+
+#[derive(Serialize, Deserialize)]
+struct SecretIdShadow {
+    sign_sk: Vec<u8>,
+    encrypt_sk: Vec<u8>,
+    public: PublicId,
+}
+
+impl Serialize for SecretId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SecretIdShadow {
+            sign_sk: (self.inner.sign).0.to_vec(),
+            encrypt_sk: (self.inner.encrypt).0.to_vec(),
+            public: self.public.clone(),
+        }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let shadow = SecretIdShadow::deserialize(deserializer)?;
+
+        if shadow.sign_sk.len() != sign::SECRETKEYBYTES
+            || shadow.encrypt_sk.len() != box_::SECRETKEYBYTES
+        {
+            return Err(DeError::custom(DeserialiseSecretError::InvalidLength));
+        }
+
+        let mut sign_sk_bytes = [0u8; sign::SECRETKEYBYTES];
+        sign_sk_bytes.copy_from_slice(&shadow.sign_sk);
+        let sign_sk = sign::SecretKey(sign_sk_bytes);
+
+        let mut encrypt_sk_bytes = [0u8; box_::SECRETKEYBYTES];
+        encrypt_sk_bytes.copy_from_slice(&shadow.encrypt_sk);
+        let encrypt_sk = box_::SecretKey(encrypt_sk_bytes);
+
+        if derive_public_id::<SodiumSuite>(&sign_sk, &encrypt_sk) != shadow.public {
+            return Err(DeError::custom(DeserialiseSecretError::PublicKeyMismatch));
+        }
+
+        Ok(SecretId {
+            public: shadow.public,
+            inner: Arc::new(SecretIdInner {
+                sign: sign_sk,
+                encrypt: encrypt_sk,
+            }),
+        })
+    }
+}
+
+impl Serialize for SharedSecretKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (self.precomputed.0).to_vec().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SharedSecretKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = Vec::<u8>::deserialize(deserializer)?;
+        if bytes.len() != box_::PRECOMPUTEDKEYBYTES {
+            return Err(DeError::custom(DeserialiseSecretError::InvalidLength));
+        }
+
+        let mut key_bytes = [0u8; box_::PRECOMPUTEDKEYBYTES];
+        key_bytes.copy_from_slice(&bytes);
+        Ok(SharedSecretKey {
+            precomputed: Arc::new(box_::PrecomputedKey(key_bytes)),
+        })
+    }
+}
@@ -0,0 +1,243 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use rust_sodium::randombytes::randombytes;
+
+/// A single share of a secret split via `split`. Any `k` distinct shards produced by the same
+/// split can be fed back into `combine` to recover the original secret.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Shard {
+    x: u8,
+    ys: Vec<u8>,
+}
+
+/// Splits `secret` into `n` shards, any `k` of which are sufficient to reconstruct it, using
+/// Shamir's secret sharing over GF(256).
+pub fn split(secret: &[u8], k: u8, n: u8) -> Result<Vec<Shard>, SplitError> {
+    if k == 0 || n == 0 || k > n {
+        return Err(SplitError::InvalidThreshold);
+    }
+
+    let mut ys: Vec<Vec<u8>> = vec![Vec::with_capacity(secret.len()); n as usize];
+    for &secret_byte in secret {
+        let mut coeffs = Vec::with_capacity(k as usize);
+        coeffs.push(secret_byte);
+        coeffs.extend(randombytes((k - 1) as usize));
+
+        for x in 1..=n {
+            ys[(x - 1) as usize].push(eval_poly(&coeffs, x));
+        }
+    }
+
+    Ok((1..=n)
+        .zip(ys)
+        .map(|(x, ys)| Shard { x, ys })
+        .collect())
+}
+
+/// Reconstructs the original secret from `shards` via Lagrange interpolation at x=0. All shards
+/// must come from the same `split` call, have distinct nonzero `x` values, and equal length.
+pub fn combine(shards: &[Shard]) -> Result<Vec<u8>, CombineError> {
+    if shards.is_empty() {
+        return Err(CombineError::NoShards);
+    }
+
+    let len = shards[0].ys.len();
+    for (i, shard) in shards.iter().enumerate() {
+        if shard.ys.len() != len {
+            return Err(CombineError::LengthMismatch);
+        }
+        if shard.x == 0 {
+            return Err(CombineError::ZeroX);
+        }
+        for other in &shards[..i] {
+            if other.x == shard.x {
+                return Err(CombineError::DuplicateX);
+            }
+        }
+    }
+
+    let mut secret = Vec::with_capacity(len);
+    for byte_idx in 0..len {
+        let mut acc = 0u8;
+        for (i, shard) in shards.iter().enumerate() {
+            let mut num = 1u8;
+            let mut den = 1u8;
+            for (j, other) in shards.iter().enumerate() {
+                if i == j {
+                    continue;
+                }
+                num = gf_mul(num, other.x);
+                den = gf_mul(den, shard.x ^ other.x);
+            }
+            acc ^= gf_mul(shard.ys[byte_idx], gf_mul(num, gf_inv(den)));
+        }
+        secret.push(acc);
+    }
+
+    Ok(secret)
+}
+
+/// Evaluates the polynomial with the given (little-endian) coefficients at `x` in GF(256).
+fn eval_poly(coeffs: &[u8], x: u8) -> u8 {
+    let mut result = 0u8;
+    for &coeff in coeffs.iter().rev() {
+        result = gf_mul(result, x) ^ coeff;
+    }
+    result
+}
+
+/// Multiplies two elements of GF(256) using the AES reduction polynomial (x^8 + x^4 + x^3 + x + 1,
+/// i.e. 0x11b).
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut p = 0u8;
+    for _ in 0..8 {
+        if b & 1 != 0 {
+            p ^= a;
+        }
+        let carry = a & 0x80;
+        a <<= 1;
+        if carry != 0 {
+            a ^= 0x1b;
+        }
+        b >>= 1;
+    }
+    p
+}
+
+/// Inverts `a` in GF(256) via Fermat's little theorem: `a^254 == a^-1` for `a != 0`.
+fn gf_inv(a: u8) -> u8 {
+    let a2 = gf_mul(a, a);
+    let a4 = gf_mul(a2, a2);
+    let a8 = gf_mul(a4, a4);
+    let a16 = gf_mul(a8, a8);
+    let a32 = gf_mul(a16, a16);
+    let a64 = gf_mul(a32, a32);
+    let a128 = gf_mul(a64, a64);
+    // a^254 = a^128 * a^64 * a^32 * a^16 * a^8 * a^4 * a^2
+    let mut result = a128;
+    result = gf_mul(result, a64);
+    result = gf_mul(result, a32);
+    result = gf_mul(result, a16);
+    result = gf_mul(result, a8);
+    result = gf_mul(result, a4);
+    gf_mul(result, a2)
+}
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum SplitError {
+        InvalidThreshold {
+            description("k must be nonzero and no greater than n")
+        }
+    }
+}
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum CombineError {
+        NoShards {
+            description("no shards given to reconstruct the secret from")
+        }
+        LengthMismatch {
+            description("shards have differing lengths")
+        }
+        ZeroX {
+            description("a shard has an x-coordinate of zero")
+        }
+        DuplicateX {
+            description("two or more shards have the same x-coordinate")
+        }
+        InvalidLength {
+            description("reconstructed secret has an unexpected length")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_combine_round_trip() {
+        let secret = b"a not-so-secret message, but long enough to span many bytes".to_vec();
+        let shards = unwrap!(split(&secret, 3, 5));
+        assert_eq!(shards.len(), 5);
+
+        let recovered = unwrap!(combine(&shards[..3]));
+        assert_eq!(recovered, secret);
+    }
+
+    #[test]
+    fn any_k_of_n_shards_reconstruct() {
+        let secret = b"shamir".to_vec();
+        let shards = unwrap!(split(&secret, 3, 5));
+
+        for combo in &[[0, 1, 2], [0, 2, 4], [1, 3, 4], [2, 3, 4]] {
+            let subset: Vec<Shard> = combo.iter().map(|&i| shards[i].clone()).collect();
+            assert_eq!(unwrap!(combine(&subset)), secret);
+        }
+    }
+
+    #[test]
+    fn fewer_than_k_shards_give_wrong_secret() {
+        let secret = b"shamir".to_vec();
+        let shards = unwrap!(split(&secret, 3, 5));
+
+        let recovered = unwrap!(combine(&shards[..2]));
+        assert_ne!(recovered, secret);
+    }
+
+    #[test]
+    fn split_rejects_invalid_threshold() {
+        assert!(match split(b"x", 0, 5) {
+            Err(SplitError::InvalidThreshold) => true,
+            _ => false,
+        });
+        assert!(match split(b"x", 6, 5) {
+            Err(SplitError::InvalidThreshold) => true,
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn combine_rejects_no_shards() {
+        assert!(match combine(&[]) {
+            Err(CombineError::NoShards) => true,
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn combine_rejects_duplicate_x() {
+        let shards = unwrap!(split(b"x", 2, 3));
+        let duplicated = vec![shards[0].clone(), shards[0].clone()];
+        assert!(match combine(&duplicated) {
+            Err(CombineError::DuplicateX) => true,
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn combine_rejects_length_mismatch() {
+        let shards = unwrap!(split(b"xy", 2, 3));
+        let mut short = shards[0].clone();
+        short.ys.pop();
+        assert!(match combine(&[short, shards[1].clone()]) {
+            Err(CombineError::LengthMismatch) => true,
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn gf_mul_and_inv_round_trip() {
+        for a in 1..=255u8 {
+            assert_eq!(gf_mul(a, gf_inv(a)), 1);
+        }
+    }
+}
@@ -0,0 +1,499 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Streaming chunked encryption for `SharedSecretKey`, for plaintexts too large to hold in
+//! memory as a single sealed blob.
+//!
+//! The wire format is a 24-byte header nonce, followed by a sequence of length-prefixed
+//! ciphertext chunks. Each chunk is sealed with `box_::seal_precomputed` using a nonce derived
+//! from the header nonce treated as a little-endian counter and incremented once per chunk, so
+//! chunks cannot be reordered or duplicated without failing to decrypt. The final chunk uses a
+//! distinct terminal nonce (the counter nonce with its top bit flipped) so that a stream
+//! truncated before its final chunk is detected rather than silently accepted.
+
+use rust_sodium::crypto::box_;
+use std::io::{self, Read, Write};
+
+use SharedSecretKey;
+
+const LEN_PREFIX_BYTES: usize = 4;
+
+impl SharedSecretKey {
+    /// Encrypts `reader` as a sequence of `chunk_size`-byte chunks, writing the sealed stream to
+    /// `writer`.
+    pub fn encrypt_stream<R: Read, W: Write>(
+        &self,
+        mut reader: R,
+        mut writer: W,
+        chunk_size: usize,
+    ) -> Result<(), EncryptStreamError> {
+        if chunk_size == 0 {
+            return Err(EncryptStreamError::InvalidChunkSize);
+        }
+
+        let header_nonce = box_::gen_nonce();
+        writer
+            .write_all(&header_nonce.0)
+            .map_err(EncryptStreamError::Io)?;
+
+        let mut current = read_chunk(&mut reader, chunk_size).map_err(EncryptStreamError::Io)?;
+        let mut index = 0u64;
+        loop {
+            let next = read_chunk(&mut reader, chunk_size).map_err(EncryptStreamError::Io)?;
+            let is_final = next.is_empty();
+            let nonce = nonce_for(&header_nonce, index, is_final);
+            let ciphertext = box_::seal_precomputed(&current, &nonce, &self.precomputed);
+            write_chunk(&mut writer, &ciphertext).map_err(EncryptStreamError::Io)?;
+
+            if is_final {
+                return Ok(());
+            }
+            current = next;
+            index += 1;
+        }
+    }
+
+    /// Decrypts a stream produced by `encrypt_stream`, writing the recovered plaintext to
+    /// `writer`. `max_chunk_size` bounds the length prefix read from the (untrusted) stream
+    /// before a chunk is allocated, so a corrupt or hostile stream can only trigger an
+    /// allocation up to `max_chunk_size + box_::MACBYTES` rather than up to 4 GiB; pass the
+    /// `chunk_size` originally given to `encrypt_stream` (or a generous upper bound on it) — the
+    /// `MACBYTES` of per-chunk authentication overhead is accounted for automatically. Fails with
+    /// `DecryptStreamError::Truncated` if the stream ends before its final chunk, with
+    /// `DecryptStreamError::ChunkTooLarge` if a chunk's declared length exceeds
+    /// `max_chunk_size + box_::MACBYTES`, and aborts immediately (without writing the offending
+    /// chunk) if any chunk fails to authenticate.
+    pub fn decrypt_stream<R: Read, W: Write>(
+        &self,
+        mut reader: R,
+        mut writer: W,
+        max_chunk_size: u32,
+    ) -> Result<(), DecryptStreamError> {
+        let mut header_bytes = [0u8; box_::NONCEBYTES];
+        reader
+            .read_exact(&mut header_bytes)
+            .map_err(DecryptStreamError::Io)?;
+        let header_nonce = box_::Nonce(header_bytes);
+
+        let mut index = 0u64;
+        loop {
+            let ciphertext = match read_framed_chunk(&mut reader, max_chunk_size)? {
+                Some(ciphertext) => ciphertext,
+                None => return Err(DecryptStreamError::Truncated),
+            };
+
+            let nonce = nonce_for(&header_nonce, index, false);
+            if let Ok(plaintext) = box_::open_precomputed(&ciphertext, &nonce, &self.precomputed) {
+                writer.write_all(&plaintext).map_err(DecryptStreamError::Io)?;
+                index += 1;
+                continue;
+            }
+
+            let final_nonce = nonce_for(&header_nonce, index, true);
+            let plaintext = box_::open_precomputed(&ciphertext, &final_nonce, &self.precomputed)
+                .map_err(|()| DecryptStreamError::DecryptVerify)?;
+            writer.write_all(&plaintext).map_err(DecryptStreamError::Io)?;
+            return Ok(());
+        }
+    }
+}
+
+/// Reads up to `chunk_size` bytes from `reader`, returning fewer (down to zero) only at EOF.
+fn read_chunk<R: Read>(reader: &mut R, chunk_size: usize) -> io::Result<Vec<u8>> {
+    let mut buf = vec![0u8; chunk_size];
+    let mut filled = 0;
+    while filled < chunk_size {
+        let read = reader.read(&mut buf[filled..])?;
+        if read == 0 {
+            break;
+        }
+        filled += read;
+    }
+    buf.truncate(filled);
+    Ok(buf)
+}
+
+fn write_chunk<W: Write>(writer: &mut W, chunk: &[u8]) -> io::Result<()> {
+    let len = chunk.len() as u32;
+    writer.write_all(&[
+        len as u8,
+        (len >> 8) as u8,
+        (len >> 16) as u8,
+        (len >> 24) as u8,
+    ])?;
+    writer.write_all(chunk)
+}
+
+/// Reads one length-prefixed chunk, returning `None` if the stream ends cleanly before the
+/// length prefix of the next chunk (i.e. at a legitimate chunk boundary). Rejects a declared
+/// length greater than `max_chunk_size + box_::MACBYTES` with `DecryptStreamError::ChunkTooLarge`
+/// before allocating, since the length prefix is attacker-controlled and read ahead of any MAC
+/// check; the `MACBYTES` allowance accounts for `encrypt_stream`'s per-chunk authentication tag,
+/// which is included in the ciphertext frame this length prefixes but not in `max_chunk_size`.
+fn read_framed_chunk<R: Read>(
+    reader: &mut R,
+    max_chunk_size: u32,
+) -> Result<Option<Vec<u8>>, DecryptStreamError> {
+    let mut len_bytes = [0u8; LEN_PREFIX_BYTES];
+    let mut filled = 0;
+    while filled < LEN_PREFIX_BYTES {
+        let read = reader
+            .read(&mut len_bytes[filled..])
+            .map_err(DecryptStreamError::Io)?;
+        if read == 0 {
+            if filled == 0 {
+                return Ok(None);
+            }
+            return Err(DecryptStreamError::Io(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "stream ended mid chunk length prefix",
+            )));
+        }
+        filled += read;
+    }
+
+    let len = u32::from(len_bytes[0])
+        | (u32::from(len_bytes[1]) << 8)
+        | (u32::from(len_bytes[2]) << 16)
+        | (u32::from(len_bytes[3]) << 24);
+    let max_frame_size = u64::from(max_chunk_size) + box_::MACBYTES as u64;
+    if u64::from(len) > max_frame_size {
+        return Err(DecryptStreamError::ChunkTooLarge);
+    }
+    let mut chunk = vec![0u8; len as usize];
+    reader
+        .read_exact(&mut chunk)
+        .map_err(DecryptStreamError::Io)?;
+    Ok(Some(chunk))
+}
+
+/// Derives the per-chunk nonce by treating `header` as a little-endian counter, adding `index`,
+/// and, for the final chunk, flipping the top bit to yield a nonce distinct from any counter
+/// value used by a non-final chunk.
+fn nonce_for(header: &box_::Nonce, index: u64, is_final: bool) -> box_::Nonce {
+    let mut bytes = header.0;
+    add_counter(&mut bytes, index);
+    if is_final {
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0x80;
+    }
+    box_::Nonce(bytes)
+}
+
+fn add_counter(bytes: &mut [u8; box_::NONCEBYTES], mut counter: u64) {
+    let mut carry = 0u16;
+    for byte in bytes.iter_mut() {
+        let sum = u16::from(*byte) + (counter & 0xff) as u16 + carry;
+        *byte = sum as u8;
+        carry = sum >> 8;
+        counter >>= 8;
+        if counter == 0 && carry == 0 {
+            break;
+        }
+    }
+}
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum EncryptStreamError {
+        InvalidChunkSize {
+            description("chunk_size must be nonzero")
+        }
+        Io(e: io::Error) {
+            description("error reading or writing the stream")
+            display("error reading or writing the stream: {}", e)
+            cause(e)
+        }
+    }
+}
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum DecryptStreamError {
+        DecryptVerify {
+            description("error decrypting/verifying a chunk")
+        }
+        Truncated {
+            description("stream ended before its final chunk")
+        }
+        ChunkTooLarge {
+            description("a chunk's declared length exceeds max_chunk_size plus the MAC overhead")
+        }
+        Io(e: io::Error) {
+            description("error reading or writing the stream")
+            display("error reading or writing the stream: {}", e)
+            cause(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use SecretId;
+
+    fn shared_pair() -> (SharedSecretKey, SharedSecretKey) {
+        let alice = SecretId::new();
+        let bob = SecretId::new();
+        (
+            unwrap!(alice.shared_key(bob.public_id())),
+            unwrap!(bob.shared_key(alice.public_id())),
+        )
+    }
+
+    #[test]
+    fn round_trip_multiple_chunks() {
+        let (alice, bob) = shared_pair();
+        let plaintext = vec![0x42u8; 10_000];
+
+        let mut encrypted = Vec::new();
+        unwrap!(alice.encrypt_stream(&plaintext[..], &mut encrypted, 1_000));
+
+        let mut decrypted = Vec::new();
+        unwrap!(bob.decrypt_stream(&encrypted[..], &mut decrypted, 1_000));
+        assert_eq!(decrypted, plaintext);
+    }
+
+    #[test]
+    fn round_trip_empty_plaintext() {
+        let (alice, bob) = shared_pair();
+
+        let mut encrypted = Vec::new();
+        unwrap!(alice.encrypt_stream(&[][..], &mut encrypted, 64));
+
+        let mut decrypted = Vec::new();
+        unwrap!(bob.decrypt_stream(&encrypted[..], &mut decrypted, 64));
+        assert!(decrypted.is_empty());
+    }
+
+    #[test]
+    fn truncated_stream_is_detected() {
+        let (alice, bob) = shared_pair();
+        let plaintext = vec![0x7u8; 5_000];
+
+        let mut encrypted = Vec::new();
+        unwrap!(alice.encrypt_stream(&plaintext[..], &mut encrypted, 1_000));
+        encrypted.truncate(encrypted.len() - 1);
+
+        let mut decrypted = Vec::new();
+        let result = bob.decrypt_stream(&encrypted[..], &mut decrypted, 1_000);
+        assert!(match result {
+            Err(DecryptStreamError::Io(_)) => true,
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn chunk_exceeding_max_size_is_rejected() {
+        let (alice, bob) = shared_pair();
+        let plaintext = vec![0x9u8; 5_000];
+
+        let mut encrypted = Vec::new();
+        unwrap!(alice.encrypt_stream(&plaintext[..], &mut encrypted, 1_000));
+
+        let mut decrypted = Vec::new();
+        let result = bob.decrypt_stream(&encrypted[..], &mut decrypted, 100);
+        assert!(match result {
+            Err(DecryptStreamError::ChunkTooLarge) => true,
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn tampered_final_chunk_fails_to_decrypt() {
+        let (alice, bob) = shared_pair();
+        let plaintext = vec![0x3u8; 100];
+
+        let mut encrypted = Vec::new();
+        unwrap!(alice.encrypt_stream(&plaintext[..], &mut encrypted, 1_000));
+        let last = encrypted.len() - 1;
+        encrypted[last] ^= 0xff;
+
+        let mut decrypted = Vec::new();
+        let result = bob.decrypt_stream(&encrypted[..], &mut decrypted, 1_000);
+        assert!(match result {
+            Err(DecryptStreamError::DecryptVerify) => true,
+            _ => false,
+        });
+    }
+}
+
+#[cfg(feature = "tokio")]
+mod async_io {
+    use super::{nonce_for, DecryptStreamError, EncryptStreamError, LEN_PREFIX_BYTES};
+    use rust_sodium::crypto::box_;
+    use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+    use SharedSecretKey;
+
+    impl SharedSecretKey {
+        /// Async counterpart of `encrypt_stream`.
+        pub async fn encrypt_stream_async<R, W>(
+            &self,
+            mut reader: R,
+            mut writer: W,
+            chunk_size: usize,
+        ) -> Result<(), EncryptStreamError>
+        where
+            R: AsyncRead + Unpin,
+            W: AsyncWrite + Unpin,
+        {
+            if chunk_size == 0 {
+                return Err(EncryptStreamError::InvalidChunkSize);
+            }
+
+            let header_nonce = box_::gen_nonce();
+            writer
+                .write_all(&header_nonce.0)
+                .await
+                .map_err(EncryptStreamError::Io)?;
+
+            let mut current = read_chunk_async(&mut reader, chunk_size)
+                .await
+                .map_err(EncryptStreamError::Io)?;
+            let mut index = 0u64;
+            loop {
+                let next = read_chunk_async(&mut reader, chunk_size)
+                    .await
+                    .map_err(EncryptStreamError::Io)?;
+                let is_final = next.is_empty();
+                let nonce = nonce_for(&header_nonce, index, is_final);
+                let ciphertext = box_::seal_precomputed(&current, &nonce, &self.precomputed);
+                write_chunk_async(&mut writer, &ciphertext)
+                    .await
+                    .map_err(EncryptStreamError::Io)?;
+
+                if is_final {
+                    return Ok(());
+                }
+                current = next;
+                index += 1;
+            }
+        }
+
+        /// Async counterpart of `decrypt_stream`.
+        pub async fn decrypt_stream_async<R, W>(
+            &self,
+            mut reader: R,
+            mut writer: W,
+            max_chunk_size: u32,
+        ) -> Result<(), DecryptStreamError>
+        where
+            R: AsyncRead + Unpin,
+            W: AsyncWrite + Unpin,
+        {
+            let mut header_bytes = [0u8; box_::NONCEBYTES];
+            reader
+                .read_exact(&mut header_bytes)
+                .await
+                .map_err(DecryptStreamError::Io)?;
+            let header_nonce = box_::Nonce(header_bytes);
+
+            let mut index = 0u64;
+            loop {
+                let ciphertext = match read_framed_chunk_async(&mut reader, max_chunk_size).await? {
+                    Some(ciphertext) => ciphertext,
+                    None => return Err(DecryptStreamError::Truncated),
+                };
+
+                let nonce = nonce_for(&header_nonce, index, false);
+                if let Ok(plaintext) =
+                    box_::open_precomputed(&ciphertext, &nonce, &self.precomputed)
+                {
+                    writer
+                        .write_all(&plaintext)
+                        .await
+                        .map_err(DecryptStreamError::Io)?;
+                    index += 1;
+                    continue;
+                }
+
+                let final_nonce = nonce_for(&header_nonce, index, true);
+                let plaintext =
+                    box_::open_precomputed(&ciphertext, &final_nonce, &self.precomputed)
+                        .map_err(|()| DecryptStreamError::DecryptVerify)?;
+                writer
+                    .write_all(&plaintext)
+                    .await
+                    .map_err(DecryptStreamError::Io)?;
+                return Ok(());
+            }
+        }
+    }
+
+    async fn read_chunk_async<R: AsyncRead + Unpin>(
+        reader: &mut R,
+        chunk_size: usize,
+    ) -> ::std::io::Result<Vec<u8>> {
+        let mut buf = vec![0u8; chunk_size];
+        let mut filled = 0;
+        while filled < chunk_size {
+            let read = reader.read(&mut buf[filled..]).await?;
+            if read == 0 {
+                break;
+            }
+            filled += read;
+        }
+        buf.truncate(filled);
+        Ok(buf)
+    }
+
+    async fn write_chunk_async<W: AsyncWrite + Unpin>(
+        writer: &mut W,
+        chunk: &[u8],
+    ) -> ::std::io::Result<()> {
+        let len = chunk.len() as u32;
+        writer
+            .write_all(&[
+                len as u8,
+                (len >> 8) as u8,
+                (len >> 16) as u8,
+                (len >> 24) as u8,
+            ])
+            .await?;
+        writer.write_all(chunk).await
+    }
+
+    async fn read_framed_chunk_async<R: AsyncRead + Unpin>(
+        reader: &mut R,
+        max_chunk_size: u32,
+    ) -> Result<Option<Vec<u8>>, DecryptStreamError> {
+        let mut len_bytes = [0u8; LEN_PREFIX_BYTES];
+        let mut filled = 0;
+        while filled < LEN_PREFIX_BYTES {
+            let read = reader
+                .read(&mut len_bytes[filled..])
+                .await
+                .map_err(DecryptStreamError::Io)?;
+            if read == 0 {
+                if filled == 0 {
+                    return Ok(None);
+                }
+                return Err(DecryptStreamError::Io(::std::io::Error::new(
+                    ::std::io::ErrorKind::UnexpectedEof,
+                    "stream ended mid chunk length prefix",
+                )));
+            }
+            filled += read;
+        }
+
+        let len = u32::from(len_bytes[0])
+            | (u32::from(len_bytes[1]) << 8)
+            | (u32::from(len_bytes[2]) << 16)
+            | (u32::from(len_bytes[3]) << 24);
+        let max_frame_size = u64::from(max_chunk_size) + box_::MACBYTES as u64;
+        if u64::from(len) > max_frame_size {
+            return Err(DecryptStreamError::ChunkTooLarge);
+        }
+        let mut chunk = vec![0u8; len as usize];
+        reader
+            .read_exact(&mut chunk)
+            .await
+            .map_err(DecryptStreamError::Io)?;
+        Ok(Some(chunk))
+    }
+}
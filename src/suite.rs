@@ -0,0 +1,226 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! The crate's cipher-suite abstraction: `PublicId`, `SecretId`, and `SharedSecretKey` are generic
+//! over a `CipherSuite`, so a downstream user can plug in, e.g., a hardware-backed or
+//! FIPS-certified backend without forking the crate — following the cipher-suite indirection in
+//! `mls-rs-core` and the `CryptoProvider` provider seam in the `rustls` `mbedtls` provider. The
+//! only suite this crate ships is `SodiumSuite`: Ed25519 signatures and X25519/XSalsa20-Poly1305
+//! sealed boxes, backed by `rust_sodium`. `PublicId`s are tagged with `CipherSuite::ID` when
+//! serialised, so that a peer deserialising one under a build supporting a different suite can
+//! detect the mismatch and fail cleanly rather than misinterpreting the key bytes.
+//!
+//! `PublicId<S>`, `SecretId<S>`, and `SharedSecretKey<S>` all default their suite parameter to
+//! `SodiumSuite`, so existing code that writes the bare (unparameterised) type names keeps working
+//! unchanged; only a caller that wants a different suite needs to name it explicitly.
+
+use maidsafe_utilities::serialisation::{deserialise, serialise};
+use rust_sodium::crypto::{box_, scalarmult, sealedbox, sign};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use PackedNonce;
+
+/// A cipher suite providing the asymmetric primitives `PublicId`/`SecretId`/`SharedSecretKey` are
+/// built on: keypair generation, detached signing, anonymous sealed-box encryption, and
+/// precomputed-key box encryption. The supertrait bounds let the associated-type-bearing structs
+/// built on this trait (`PublicId<S>`, etc.) derive `Debug`/`Clone`/`Eq`/`Hash`/`Ord`/`Serialize`/
+/// `Deserialize` generically over any `S: CipherSuite`; a suite marker type (like `SodiumSuite`)
+/// is zero-sized, so implementing them is trivial.
+pub trait CipherSuite:
+    Sized + Debug + Clone + PartialEq + Eq + Hash + PartialOrd + Ord + Serialize + DeserializeOwned
+{
+    /// Byte identifying this suite in a serialised `PublicId`.
+    const ID: u8;
+
+    /// Byte length of `SignSecretKey`, for (de)serialising `SecretId::shares`/`from_shares`.
+    const SIGN_SECRET_KEY_BYTES: usize;
+    /// Byte length of `EncryptSecretKey`, for (de)serialising `SecretId::shares`/`from_shares`.
+    const ENCRYPT_SECRET_KEY_BYTES: usize;
+
+    type SignPublicKey: Debug
+        + Clone
+        + PartialEq
+        + Eq
+        + Hash
+        + PartialOrd
+        + Ord
+        + Serialize
+        + DeserializeOwned;
+    type SignSecretKey: Debug + Clone + PartialEq + Eq;
+    type Signature: Debug
+        + Clone
+        + PartialEq
+        + Eq
+        + Hash
+        + PartialOrd
+        + Ord
+        + Serialize
+        + DeserializeOwned;
+    type EncryptPublicKey: Debug
+        + Clone
+        + PartialEq
+        + Eq
+        + Hash
+        + PartialOrd
+        + Ord
+        + Serialize
+        + DeserializeOwned;
+    type EncryptSecretKey: Debug + Clone + PartialEq + Eq;
+    type PrecomputedKey: Debug + Clone + PartialEq + Eq;
+
+    fn gen_sign_keypair() -> (Self::SignPublicKey, Self::SignSecretKey);
+    fn gen_encrypt_keypair() -> (Self::EncryptPublicKey, Self::EncryptSecretKey);
+    fn sign_detached(data: &[u8], secret_key: &Self::SignSecretKey) -> Self::Signature;
+    fn verify_detached(
+        signature: &Self::Signature,
+        data: &[u8],
+        public_key: &Self::SignPublicKey,
+    ) -> bool;
+    fn seal_anonymous(plaintext: &[u8], public_key: &Self::EncryptPublicKey) -> Vec<u8>;
+    fn open_anonymous(
+        ciphertext: &[u8],
+        public_key: &Self::EncryptPublicKey,
+        secret_key: &Self::EncryptSecretKey,
+    ) -> Result<Vec<u8>, ()>;
+    fn precompute(
+        their_public_key: &Self::EncryptPublicKey,
+        our_secret_key: &Self::EncryptSecretKey,
+    ) -> Self::PrecomputedKey;
+    fn seal_precomputed(plaintext: &[u8], key: &Self::PrecomputedKey) -> Vec<u8>;
+    fn open_precomputed(encoded: &[u8], key: &Self::PrecomputedKey) -> Result<Vec<u8>, ()>;
+
+    /// Derives the public keys matching a pair of secret keys, for `SecretId::from_shares`.
+    fn derive_public_keys(
+        sign_sk: &Self::SignSecretKey,
+        encrypt_sk: &Self::EncryptSecretKey,
+    ) -> (Self::SignPublicKey, Self::EncryptPublicKey);
+
+    /// Parses a `SignSecretKey` out of exactly `SIGN_SECRET_KEY_BYTES` bytes.
+    fn sign_secret_key_from_bytes(bytes: &[u8]) -> Self::SignSecretKey;
+    /// Parses an `EncryptSecretKey` out of exactly `ENCRYPT_SECRET_KEY_BYTES` bytes.
+    fn encrypt_secret_key_from_bytes(bytes: &[u8]) -> Self::EncryptSecretKey;
+    fn sign_secret_key_bytes(key: &Self::SignSecretKey) -> &[u8];
+    fn encrypt_secret_key_bytes(key: &Self::EncryptSecretKey) -> &[u8];
+}
+
+/// The cipher suite this crate ships: Ed25519 signatures, X25519/XSalsa20-Poly1305 sealed boxes,
+/// backed by `rust_sodium`. A zero-sized marker type — all state lives in the
+/// `PublicId`/`SecretId`/`SharedSecretKey` it parameterises.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct SodiumSuite;
+
+impl CipherSuite for SodiumSuite {
+    const ID: u8 = 0;
+    const SIGN_SECRET_KEY_BYTES: usize = sign::SECRETKEYBYTES;
+    const ENCRYPT_SECRET_KEY_BYTES: usize = box_::SECRETKEYBYTES;
+
+    type SignPublicKey = sign::PublicKey;
+    type SignSecretKey = sign::SecretKey;
+    type Signature = sign::Signature;
+    type EncryptPublicKey = box_::PublicKey;
+    type EncryptSecretKey = box_::SecretKey;
+    type PrecomputedKey = box_::PrecomputedKey;
+
+    fn gen_sign_keypair() -> (sign::PublicKey, sign::SecretKey) {
+        sign::gen_keypair()
+    }
+
+    fn gen_encrypt_keypair() -> (box_::PublicKey, box_::SecretKey) {
+        box_::gen_keypair()
+    }
+
+    fn sign_detached(data: &[u8], secret_key: &sign::SecretKey) -> sign::Signature {
+        sign::sign_detached(data, secret_key)
+    }
+
+    fn verify_detached(
+        signature: &sign::Signature,
+        data: &[u8],
+        public_key: &sign::PublicKey,
+    ) -> bool {
+        sign::verify_detached(signature, data, public_key)
+    }
+
+    fn seal_anonymous(plaintext: &[u8], public_key: &box_::PublicKey) -> Vec<u8> {
+        sealedbox::seal(plaintext, public_key)
+    }
+
+    fn open_anonymous(
+        ciphertext: &[u8],
+        public_key: &box_::PublicKey,
+        secret_key: &box_::SecretKey,
+    ) -> Result<Vec<u8>, ()> {
+        sealedbox::open(ciphertext, public_key, secret_key)
+    }
+
+    fn precompute(
+        their_public_key: &box_::PublicKey,
+        our_secret_key: &box_::SecretKey,
+    ) -> box_::PrecomputedKey {
+        box_::precompute(their_public_key, our_secret_key)
+    }
+
+    fn seal_precomputed(plaintext: &[u8], key: &box_::PrecomputedKey) -> Vec<u8> {
+        let nonce = box_::gen_nonce();
+        let ciphertext = box_::seal_precomputed(plaintext, &nonce, key);
+        unwrap!(serialise(&PackedNonce {
+            nonce: nonce.0,
+            ciphertext,
+            pow_nonce: None,
+        }))
+    }
+
+    fn open_precomputed(encoded: &[u8], key: &box_::PrecomputedKey) -> Result<Vec<u8>, ()> {
+        let PackedNonce {
+            nonce, ciphertext, ..
+        } = deserialise(encoded).map_err(|_| ())?;
+        box_::open_precomputed(&ciphertext, &box_::Nonce(nonce), key)
+    }
+
+    fn derive_public_keys(
+        sign_sk: &sign::SecretKey,
+        encrypt_sk: &box_::SecretKey,
+    ) -> (sign::PublicKey, box_::PublicKey) {
+        // An Ed25519 secret key is libsodium's `seed || public_key` packing, so the public key is
+        // always the trailing `PUBLICKEYBYTES` of the secret key — this holds regardless of
+        // whether a particular `rust_sodium` version exposes a `SecretKey::public_key()`
+        // convenience method, so we don't depend on one.
+        let offset = sign::SECRETKEYBYTES - sign::PUBLICKEYBYTES;
+        let mut sign_pk_bytes = [0u8; sign::PUBLICKEYBYTES];
+        sign_pk_bytes.copy_from_slice(&sign_sk.0[offset..]);
+        let sign_pk = sign::PublicKey(sign_pk_bytes);
+
+        let encrypt_pk =
+            box_::PublicKey(scalarmult::scalarmult_base(&scalarmult::Scalar(encrypt_sk.0)).0);
+
+        (sign_pk, encrypt_pk)
+    }
+
+    fn sign_secret_key_from_bytes(bytes: &[u8]) -> sign::SecretKey {
+        let mut array = [0u8; sign::SECRETKEYBYTES];
+        array.copy_from_slice(bytes);
+        sign::SecretKey(array)
+    }
+
+    fn encrypt_secret_key_from_bytes(bytes: &[u8]) -> box_::SecretKey {
+        let mut array = [0u8; box_::SECRETKEYBYTES];
+        array.copy_from_slice(bytes);
+        box_::SecretKey(array)
+    }
+
+    fn sign_secret_key_bytes(key: &sign::SecretKey) -> &[u8] {
+        &key.0
+    }
+
+    fn encrypt_secret_key_bytes(key: &box_::SecretKey) -> &[u8] {
+        &key.0
+    }
+}